@@ -1,8 +1,10 @@
 extern crate bindgen;
-extern crate num_cpus;
+extern crate cc;
+extern crate cmake;
 
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
 /// Gets the absolute path of the parent directory. This will be used for
 /// specifying the cmake directory and linking the library files from there.
@@ -15,50 +17,242 @@ fn get_parent_dir() -> std::io::Result<String> {
     Ok(format!("{}", path.to_string_lossy()))
 }
 
+/// Names of the static bundles that `UKV_BUILD_BUNDLES` produces, gated by
+/// the matching Cargo feature.
+fn enabled_bundles() -> Vec<&'static str> {
+    let mut bundles = Vec::new();
+    if cfg!(feature = "umem") {
+        bundles.push("umem_bundle");
+    }
+    if cfg!(feature = "leveldb") {
+        bundles.push("leveldb_bundle");
+    }
+    if cfg!(feature = "rocksdb") {
+        bundles.push("rocksdb_bundle");
+    }
+    bundles
+}
+
+/// The C/C++ compilers (and, when cross-compiling, the sysroot) CMake and
+/// bindgen should use for a given Cargo `TARGET`.
+struct Toolchain {
+    cc: PathBuf,
+    cxx: PathBuf,
+    sysroot: Option<PathBuf>,
+}
+
+/// Mirrors rustc bootstrap's `cc_detect`: honor `CC`/`CXX` (and their
+/// target-scoped `CC_<target>`/`CXX_<target>` variants, as set by
+/// cross-compilation wrapper scripts) before falling back to the `cc`
+/// crate's own toolchain discovery, which already knows how to find a
+/// target-appropriate compiler from `PATH` conventions (e.g.
+/// `aarch64-linux-gnu-gcc`).
+fn detect_toolchain(target: &str) -> Toolchain {
+    let target_env = |var: &str| -> Option<String> {
+        std::env::var(format!("{}_{}", var, target))
+            .or_else(|_| std::env::var(format!("{}_{}", var, target.replace('-', "_"))))
+            .or_else(|_| std::env::var(var))
+            .ok()
+    };
+
+    let cc = target_env("CC")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| cc::Build::new().target(target).get_compiler().path().to_path_buf());
+    let cxx = target_env("CXX")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            cc::Build::new()
+                .cpp(true)
+                .target(target)
+                .get_compiler()
+                .path()
+                .to_path_buf()
+        });
+    let sysroot = target_env("SYSROOT").map(PathBuf::from);
+
+    Toolchain { cc, cxx, sysroot }
+}
+
+/// Tells Cargo to re-invoke `build.rs` when any of the env vars
+/// `detect_toolchain` probes for `target` change. Cargo only reruns on an
+/// env change for variables it's explicitly told about, and emitting even
+/// one `cargo:rerun-if-changed` already disables its default "rerun on any
+/// package change" behavior.
+fn emit_toolchain_rerun_directives(target: &str) {
+    let target_underscored = target.replace('-', "_");
+    for var in ["CC", "CXX", "SYSROOT"] {
+        println!("cargo:rerun-if-env-changed={}", var);
+        println!("cargo:rerun-if-env-changed={}_{}", var, target);
+        if target_underscored != target {
+            println!("cargo:rerun-if-env-changed={}_{}", var, target_underscored);
+        }
+    }
+}
+
+/// Maps a Rust target triple to the `CMAKE_SYSTEM_NAME`/`CMAKE_SYSTEM_PROCESSOR`
+/// pair CMake expects: the processor is the triple's first component and the
+/// system name comes from the OS it mentions, e.g.
+/// `aarch64-unknown-linux-gnu` -> (`Linux`, `aarch64`).
+fn cmake_system(target: &str) -> (&'static str, String) {
+    let mut parts = target.split('-');
+    let arch = parts.next().unwrap_or("x86_64").to_string();
+    let system_name = if target.contains("linux") {
+        "Linux"
+    } else if target.contains("darwin") || target.contains("apple") {
+        "Darwin"
+    } else if target.contains("windows") {
+        "Windows"
+    } else {
+        "Generic"
+    };
+    (system_name, arch)
+}
+
+/// Walks `root` recursively and returns the most recent modification time of
+/// any file found. An entry whose metadata or mtime can't be read makes the
+/// whole walk return that error.
+fn newest_mtime(root: &Path) -> std::io::Result<Option<SystemTime>> {
+    if !root.exists() {
+        return Ok(None);
+    }
+    let mut newest = None;
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        if dir.is_file() {
+            let mtime = dir.metadata()?.modified()?;
+            newest = Some(newest.map_or(mtime, |cur: SystemTime| cur.max(mtime)));
+            continue;
+        }
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else {
+                let mtime = path.metadata()?.modified()?;
+                newest = Some(newest.map_or(mtime, |cur: SystemTime| cur.max(mtime)));
+            }
+        }
+    }
+    Ok(newest)
+}
+
+/// Returns the oldest modification time among the given files, or `None` if
+/// any of them is missing (which means the bundle hasn't been built yet).
+fn oldest_mtime(paths: &[PathBuf]) -> std::io::Result<Option<SystemTime>> {
+    let mut oldest = None;
+    for path in paths {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let mtime = path.metadata()?.modified()?;
+        oldest = Some(oldest.map_or(mtime, |cur: SystemTime| cur.min(mtime)));
+    }
+    Ok(oldest)
+}
+
+/// `cmake::Config::build()` does not run an install step when a
+/// `build_target` is set (we use `"all"`), so nothing is installed into the
+/// directory it returns (`OUT_DIR`); the CMake build tree itself lives in
+/// its `build` subdirectory. Inside that build tree the project's own
+/// `CMakeLists.txt` places static bundles under a nested `build/lib`.
+fn bundle_lib_dir(dst: &Path) -> PathBuf {
+    dst.join("build").join("build").join("lib")
+}
+
+/// Mirrors the up-to-date check used by the rustc bootstrap: compares the
+/// newest mtime among the CMake project inputs against the oldest mtime
+/// among the bundle archives we expect `cmake --build` to produce. If every
+/// output is newer than every input, the native build step can be skipped
+/// entirely.
+fn bundles_are_up_to_date(src: &Path, lib_dir: &Path, bundles: &[&str]) -> std::io::Result<bool> {
+    let outputs: Vec<PathBuf> = bundles
+        .iter()
+        .map(|bundle| lib_dir.join(format!("lib{}.a", bundle)))
+        .collect();
+    let oldest_output = match oldest_mtime(&outputs)? {
+        Some(mtime) => mtime,
+        None => return Ok(false),
+    };
+
+    let inputs = [
+        src.join("CMakeLists.txt"),
+        src.join("include"),
+        src.join("src"),
+    ];
+    let mut newest_input = None;
+    for input in &inputs {
+        if let Some(mtime) = newest_mtime(input)? {
+            newest_input = Some(newest_input.map_or(mtime, |cur: SystemTime| cur.max(mtime)));
+        }
+    }
+
+    Ok(match newest_input {
+        Some(newest_input) => oldest_output > newest_input,
+        None => true,
+    })
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let src = get_parent_dir()?;
-    let cmd = std::process::Command::new("cmake")
-        .arg("-DCMAKE_C_COMPILER=gcc") // this works, however, changing to clang will fail
-        .arg("-DCMAKE_CXX_COMPILER=g++") // this works, however, changing to clang++ will fail
-        .arg("-DUKV_BUILD_TESTS=0")
-        .arg("-DUKV_BUILD_BENCHMARKS=0")
-        .arg("-DUKV_BUILD_BUNDLES=1")
-        .arg(format!(
-            "-DUKV_BUILD_ENGINE_UMEM={}",
-            if cfg!(feature = "umem") { 1 } else { 0 }
-        ))
-        .arg(format!(
-            "-DUKV_BUILD_ENGINE_LEVELDB={}",
-            if cfg!(feature = "leveldb") { 1 } else { 0 }
-        ))
-        .arg(format!(
-            "-DUKV_BUILD_ENGINE_ROCKSDB={}",
-            if cfg!(feature = "rocksdb") { 1 } else { 0 }
-        ))
-        .arg("-DUKV_BUILD_API_FLIGHT_CLIENT=0")
-        .arg("-DUKV_BUILD_API_FLIGHT_SERVER=0")
-        .arg("-B ./build_release")
-        .current_dir(&src)
-        .output()
-        .expect("Could not spawn a `cmake` process");
+    let src_path = Path::new(&src);
+    let out_dir = PathBuf::from(std::env::var("OUT_DIR")?);
+    let bundles = enabled_bundles();
+    if bundles.is_empty() {
+        return Err(
+            "ukv: no storage engine selected; enable at least one of the `umem`, `leveldb`, or `rocksdb` Cargo features"
+                .into(),
+        );
+    }
+    // `cmake::Config::build()` returns `OUT_DIR` unchanged when `.out_dir()`
+    // isn't overridden (as below), so this is the same directory the build
+    // branch will derive from the real `dst` it captures.
+    let mut lib_dir = bundle_lib_dir(&out_dir);
 
-    println!("CMake: {}", cmd.status);
-    std::io::stdout().write_all(&cmd.stdout)?;
-    std::io::stderr().write_all(&cmd.stderr)?;
+    let target = std::env::var("TARGET")?;
+    let host = std::env::var("HOST")?;
+    let is_cross = target != host;
+    let toolchain = detect_toolchain(&target);
+    emit_toolchain_rerun_directives(&target);
 
-    let cmd = std::process::Command::new("make")
-        .arg(format!("-j{}", num_cpus::get()))
-        .arg("-C")
-        .arg("./build_release")
-        .current_dir(&src)
-        .output()
-        .expect("Could not spawn a `make` process");
+    if bundles_are_up_to_date(src_path, &lib_dir, &bundles)? {
+        println!("cargo:warning=ukv: bundle archives are up to date, skipping cmake+make");
+    } else {
+        let mut config = cmake::Config::new(&src);
+        config
+            .define("UKV_BUILD_TESTS", "0")
+            .define("UKV_BUILD_BENCHMARKS", "0")
+            .define("UKV_BUILD_BUNDLES", "1")
+            .define("UKV_BUILD_ENGINE_UMEM", if cfg!(feature = "umem") { "1" } else { "0" })
+            .define(
+                "UKV_BUILD_ENGINE_LEVELDB",
+                if cfg!(feature = "leveldb") { "1" } else { "0" },
+            )
+            .define(
+                "UKV_BUILD_ENGINE_ROCKSDB",
+                if cfg!(feature = "rocksdb") { "1" } else { "0" },
+            )
+            .define("UKV_BUILD_API_FLIGHT_CLIENT", "0")
+            .define("UKV_BUILD_API_FLIGHT_SERVER", "0")
+            .define("CMAKE_C_COMPILER", &toolchain.cc)
+            .define("CMAKE_CXX_COMPILER", &toolchain.cxx)
+            .build_target("all");
 
-    println!("Make: {}", cmd.status);
-    std::io::stdout().write_all(&cmd.stdout)?;
-    std::io::stderr().write_all(&cmd.stderr)?;
+        if is_cross {
+            let (system_name, processor) = cmake_system(&target);
+            config
+                .define("CMAKE_SYSTEM_NAME", system_name)
+                .define("CMAKE_SYSTEM_PROCESSOR", &processor);
+            if let Some(sysroot) = &toolchain.sysroot {
+                config.define("CMAKE_SYSROOT", sysroot);
+            }
+        }
 
-    let cmd = std::process::Command::new("gcc")
+        let dst = config.build();
+        lib_dir = bundle_lib_dir(&dst);
+    }
+
+    let cmd = std::process::Command::new(&toolchain.cc)
         .arg("-E")
         .arg("-I")
         .arg(format!("{}/include", &src))
@@ -68,25 +262,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .arg("-o")
         .arg("wrapper.expanded.h")
         .output()
-        .expect("Could not spawn a `gcc` expansion process");
+        .expect("Could not spawn the detected C compiler for header expansion");
 
-    println!("GCC: {}", cmd.status);
+    println!("{} -E wrapper.h: {}", toolchain.cc.display(), cmd.status);
     std::io::stdout().write_all(&cmd.stdout)?;
     std::io::stderr().write_all(&cmd.stderr)?;
 
-    println!(
-        "cargo:rustc-link-search=native={}/build_release/build/lib",
-        &src
-    );
-    #[cfg(feature = "umem")]
-    println!("cargo:rustc-link-lib=static=umem_bundle");
-    #[cfg(feature = "rocksdb")]
-    println!("cargo:rustc-link-lib=static=rocksdb_bundle");
-    #[cfg(feature = "leveldb")]
-    println!("cargo:rustc-link-lib=static=leveldb_bundle");
-
-    let output = PathBuf::from(std::env::var("OUT_DIR")?);
-    let bindings = bindgen::Builder::default()
+    println!("cargo:rustc-link-search=native={}", lib_dir.display());
+    for bundle in &bundles {
+        println!("cargo:rustc-link-lib=static={}", bundle);
+    }
+
+    let mut bindgen_builder = bindgen::Builder::default()
         .header("wrapper.expanded.h")
         .size_t_is_usize(true)
         .enable_cxx_namespaces()
@@ -97,10 +284,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         .clang_arg(format!("{}/include", &src))
         .clang_arg("-I")
         .clang_arg(format!("{}/include/ukv", &src))
-        .clang_arg("--verbose")
-        .generate()?;
+        .clang_arg("--verbose");
+
+    if is_cross {
+        bindgen_builder = bindgen_builder.clang_arg(format!("--target={}", target));
+        if let Some(sysroot) = &toolchain.sysroot {
+            bindgen_builder = bindgen_builder.clang_arg(format!("--sysroot={}", sysroot.display()));
+        }
+    }
+
+    let bindings = bindgen_builder.generate()?;
 
-    bindings.write_to_file(output.join("bindings.rs"))?;
+    bindings.write_to_file(out_dir.join("bindings.rs"))?;
     println!("cargo:rerun-if-changed=wrapper.h");
+    println!("cargo:rerun-if-changed=../CMakeLists.txt");
+    println!("cargo:rerun-if-changed=../include");
+    println!("cargo:rerun-if-changed=../src");
     Ok(())
 }
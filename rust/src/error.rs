@@ -1,7 +1,49 @@
 use thiserror::Error;
 
+use crate::bindings;
+use crate::Engine;
+
 #[derive(Error, Debug)]
 pub enum DataStoreError {
 	#[error("unknown data store error")]
 	Unknown,
+
+	#[error("engine {0:?} was not compiled into this build")]
+	EngineNotCompiled(Engine),
+
+	#[error("key not found: {0}")]
+	NotFound(String),
+
+	#[error("out of memory: {0}")]
+	OutOfMemory(String),
+
+	#[error("backend error: {0}")]
+	Backend(String),
+
+	#[error("database handle was never successfully initialized")]
+	NotInitialized,
+}
+
+/// Translates the `ukv_error_t` a task struct (`ukv_read_t`, `ukv_write_t`, ...)
+/// writes to its `error` field into a [`DataStoreError`], freeing the
+/// underlying C string afterwards. A `NULL` error means the call succeeded.
+pub(crate) fn check(error: bindings::ukv_error_t) -> Result<(), DataStoreError> {
+	if error.is_null() {
+		return Ok(());
+	}
+
+	let message = unsafe { std::ffi::CStr::from_ptr(error) }
+		.to_string_lossy()
+		.into_owned();
+
+	let result = if message.contains("not found") {
+		DataStoreError::NotFound(message)
+	} else if message.contains("out of memory") || message.contains("bad_alloc") {
+		DataStoreError::OutOfMemory(message)
+	} else {
+		DataStoreError::Backend(message)
+	};
+
+	unsafe { bindings::ukv_error_free(error) };
+	Err(result)
 }
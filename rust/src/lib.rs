@@ -4,30 +4,101 @@
 
 mod error;
 
+pub use error::DataStoreError;
+
 pub mod bindings {
 	include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
 }
 
 type UkvDatabaseInitType = bindings::ukv_database_init_t;
 
+/// Selects which compiled-in storage engine a [`Database`] should open.
+///
+/// Only the engine(s) whose matching Cargo feature (`umem`, `leveldb`,
+/// `rocksdb`) was enabled at build time are actually linked into the
+/// binary; `build.rs` refuses to compile at all if none of them are on.
+/// Requesting an `Engine` that wasn't compiled in is a runtime error rather
+/// than a link error, since the crate may be built with a subset of engines
+/// for a given deployment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+	Umem,
+	LevelDb,
+	RocksDb,
+}
+
+impl Engine {
+	fn is_compiled_in(self) -> bool {
+		match self {
+			Engine::Umem => cfg!(feature = "umem"),
+			Engine::LevelDb => cfg!(feature = "leveldb"),
+			Engine::RocksDb => cfg!(feature = "rocksdb"),
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			Engine::Umem => "umem",
+			Engine::LevelDb => "leveldb",
+			Engine::RocksDb => "rocksdb",
+		}
+	}
+}
+
+/// Injects an `"engine"` selector into a JSON `config` string. A binary can
+/// link more than one engine bundle at once (one per enabled feature), so
+/// the native `ukv_database_init` call needs this to know which one to open.
+///
+/// Rejects a `config` that isn't a JSON object, or that already carries an
+/// `"engine"` key, rather than silently dropping or shadowing it.
+fn config_with_engine(engine: Engine, config: &str) -> Result<String, error::DataStoreError> {
+	let trimmed = config.trim();
+	if trimmed.is_empty() {
+		return Ok(format!("{{\"engine\":\"{}\"}}", engine.name()));
+	}
+
+	let body = trimmed
+		.strip_prefix('{')
+		.and_then(|rest| rest.strip_suffix('}'))
+		.ok_or_else(|| error::DataStoreError::Backend(format!("config is not a JSON object: {}", config)))?
+		.trim();
+
+	if body.is_empty() {
+		return Ok(format!("{{\"engine\":\"{}\"}}", engine.name()));
+	}
+	if body.contains("\"engine\"") {
+		return Err(error::DataStoreError::Backend(
+			"config must not set \"engine\" itself; pass it via Database::open's `engine` argument".to_string(),
+		));
+	}
+
+	Ok(format!("{{\"engine\":\"{}\",{}}}", engine.name(), body))
+}
+
 pub struct Database {
 	pub db: UkvDatabaseInitType,
+	/// Whether `ukv_database_init` actually produced a usable handle. When
+	/// `false`, `db.db` may still hold the placeholder pointer it was
+	/// constructed with, so `Drop` must not pass it to `ukv_database_free`.
+	initialized: bool,
 }
 
 impl Default for Database {
 	fn default() -> Self {
 		let config: *const _ = std::ffi::CString::default().as_ptr();
-		let error: *mut _ = &mut std::ffi::CString::default().as_ptr();
+		let mut c_error: bindings::ukv_error_t = std::ptr::null_mut();
 		let void_fn = &mut () as *mut _ as *mut std::ffi::c_void;
 		let mut db = UkvDatabaseInitType {
-			error,
+			error: &mut c_error,
 			config,
 			db: void_fn as _,
 		};
 
 		unsafe { bindings::ukv_database_init(&mut db) };
+		let initialized = error::check(c_error).is_ok();
 		Self {
 			db,
+			initialized,
 		}
 	}
 }
@@ -36,27 +107,208 @@ impl Database {
 	/// Open a new database using ukv_database_init()
 	pub fn new() -> Self {
 		let config: *const _ = std::ffi::CString::default().as_ptr();
-		let error: *mut _ = &mut std::ffi::CString::default().as_ptr();
+		let mut c_error: bindings::ukv_error_t = std::ptr::null_mut();
 		let void_fn = &mut () as *mut _ as *mut std::ffi::c_void;
 		let mut db = UkvDatabaseInitType {
-			error,
+			error: &mut c_error,
 			config,
 			db: void_fn as _,
 		};
 
 		unsafe { bindings::ukv_database_init(&mut db) };
+		let initialized = error::check(c_error).is_ok();
 		Self {
 			db,
+			initialized,
 		}
 	}
 
-	pub fn contains_key() {}
+	/// Open a database backed by the given `engine`, via `ukv_database_init()`.
+	///
+	/// Returns [`DataStoreError::EngineNotCompiled`] if the crate wasn't built
+	/// with the Cargo feature matching `engine`, or whatever [`DataStoreError`]
+	/// the native call reports if the open itself fails.
+	pub fn open(engine: Engine, config: &str) -> Result<Self, error::DataStoreError> {
+		if !engine.is_compiled_in() {
+			return Err(error::DataStoreError::EngineNotCompiled(engine));
+		}
 
-	// Close a database using ukv_database_free()
-	pub fn close() -> Result<(), error::DataStoreError> {
+		let config = config_with_engine(engine, config)?;
+		let config = std::ffi::CString::new(config).map_err(|_| error::DataStoreError::Unknown)?;
+		let config: *const _ = config.as_ptr();
+		let mut c_error: bindings::ukv_error_t = std::ptr::null_mut();
 		let void_fn = &mut () as *mut _ as *mut std::ffi::c_void;
-		unsafe { bindings::ukv_database_free(void_fn) };
+		let mut db = UkvDatabaseInitType {
+			error: &mut c_error,
+			config,
+			db: void_fn as _,
+		};
+
+		unsafe { bindings::ukv_database_init(&mut db) };
+		error::check(c_error)?;
+		Ok(Self {
+			db,
+			initialized: true,
+		})
+	}
+
+	/// Reads the value stored under `key` in `collection`, via `ukv_read()`.
+	/// Returns `Ok(None)` if the key is absent rather than an error.
+	pub fn get(
+		&self,
+		collection: bindings::ukv_collection_t,
+		key: bindings::ukv_key_t,
+	) -> Result<Option<Vec<u8>>, error::DataStoreError> {
+		if !self.initialized {
+			return Err(error::DataStoreError::NotInitialized);
+		}
+
+		let mut c_error: bindings::ukv_error_t = std::ptr::null_mut();
+		let mut arena: bindings::ukv_arena_t = std::ptr::null_mut();
+		let mut presences: *mut bindings::ukv_octet_t = std::ptr::null_mut();
+		let mut offsets: *mut bindings::ukv_length_t = std::ptr::null_mut();
+		let mut lengths: *mut bindings::ukv_length_t = std::ptr::null_mut();
+		let mut values: bindings::ukv_bytes_ptr_t = std::ptr::null_mut();
+
+		let mut read = bindings::ukv_read_t {
+			db: self.db.db,
+			transaction: std::ptr::null_mut(),
+			arena: &mut arena,
+			options: 0,
+			tasks_count: 1,
+			collections: &collection,
+			collections_stride: 0,
+			keys: &key,
+			keys_stride: 0,
+			presences: &mut presences,
+			offsets: &mut offsets,
+			lengths: &mut lengths,
+			values: &mut values,
+			error: &mut c_error,
+		};
+
+		unsafe { bindings::ukv_read(&mut read) };
+		let checked = error::check(c_error);
+
+		let result = checked.map(|()| {
+			// `presences` is a bitmap (one bit per task), not one byte per task;
+			// for `tasks_count == 1` the answer lives in bit 0.
+			let present = presences.is_null() || unsafe { *presences } & 1 != 0;
+			if !present {
+				None
+			} else {
+				let length = unsafe { *lengths } as usize;
+				let offset = unsafe { *offsets } as usize;
+				let value = if length == 0 {
+					Vec::new()
+				} else {
+					unsafe { std::slice::from_raw_parts(values.add(offset), length) }.to_vec()
+				};
+				Some(value)
+			}
+		});
+
+		unsafe { bindings::ukv_arena_free(arena) };
+		result
+	}
+
+	/// Writes `value` under `key` in `collection`, via `ukv_write()`.
+	pub fn set(
+		&self,
+		collection: bindings::ukv_collection_t,
+		key: bindings::ukv_key_t,
+		value: &[u8],
+	) -> Result<(), error::DataStoreError> {
+		if !self.initialized {
+			return Err(error::DataStoreError::NotInitialized);
+		}
+
+		let mut c_error: bindings::ukv_error_t = std::ptr::null_mut();
+		let mut arena: bindings::ukv_arena_t = std::ptr::null_mut();
+		let length = value.len() as bindings::ukv_length_t;
+		let value_ptr = value.as_ptr();
+
+		let mut write = bindings::ukv_write_t {
+			db: self.db.db,
+			transaction: std::ptr::null_mut(),
+			arena: &mut arena,
+			options: 0,
+			tasks_count: 1,
+			collections: &collection,
+			collections_stride: 0,
+			keys: &key,
+			keys_stride: 0,
+			presences: std::ptr::null(),
+			offsets: std::ptr::null(),
+			lengths: &length,
+			values: &value_ptr,
+			error: &mut c_error,
+		};
+
+		unsafe { bindings::ukv_write(&mut write) };
+		let checked = error::check(c_error);
+		unsafe { bindings::ukv_arena_free(arena) };
+		checked
+	}
+
+	/// Deletes `key` from `collection` by writing a null value, via `ukv_write()`.
+	pub fn remove(
+		&self,
+		collection: bindings::ukv_collection_t,
+		key: bindings::ukv_key_t,
+	) -> Result<(), error::DataStoreError> {
+		if !self.initialized {
+			return Err(error::DataStoreError::NotInitialized);
+		}
+
+		let mut c_error: bindings::ukv_error_t = std::ptr::null_mut();
+		let mut arena: bindings::ukv_arena_t = std::ptr::null_mut();
+
+		let mut write = bindings::ukv_write_t {
+			db: self.db.db,
+			transaction: std::ptr::null_mut(),
+			arena: &mut arena,
+			options: 0,
+			tasks_count: 1,
+			collections: &collection,
+			collections_stride: 0,
+			keys: &key,
+			keys_stride: 0,
+			presences: std::ptr::null(),
+			offsets: std::ptr::null(),
+			lengths: std::ptr::null(),
+			values: std::ptr::null(),
+			error: &mut c_error,
+		};
+
+		unsafe { bindings::ukv_write(&mut write) };
+		let checked = error::check(c_error);
+		unsafe { bindings::ukv_arena_free(arena) };
+		checked
+	}
+
+	/// Checks whether `key` exists in `collection`.
+	pub fn contains_key(
+		&self,
+		collection: bindings::ukv_collection_t,
+		key: bindings::ukv_key_t,
+	) -> Result<bool, error::DataStoreError> {
+		Ok(self.get(collection, key)?.is_some())
+	}
 
+	/// Close a database, releasing it via `ukv_database_free()`.
+	///
+	/// The actual release happens in `Drop`; this just consumes `self` so
+	/// the handle can't be used afterwards.
+	pub fn close(self) -> Result<(), error::DataStoreError> {
 		Ok(())
 	}
 }
+
+impl Drop for Database {
+	fn drop(&mut self) {
+		if self.initialized {
+			unsafe { bindings::ukv_database_free(self.db.db) };
+		}
+	}
+}